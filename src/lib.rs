@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde_json::Value;
 use thiserror::Error;
 
@@ -13,55 +15,705 @@ pub enum ToSqlError {
     MissingOperator(String),
     #[error("invalid stage `{0}`")]
     InvalidStage(Value),
+    #[error("stage cannot be placed in the pipeline: `{0}`")]
+    UnplaceableStage(String),
+    #[error("unsupported regex flag `{0}`")]
+    UnsupportedRegexFlag(char),
+    #[error("exclusion-only $project is not supported: `{0}`")]
+    UnsupportedExclusionProjection(Value),
+}
+
+/// A target SQL engine, controlling how identifiers, string literals, and
+/// regex operators are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Postgres,
+    MySql,
+    Sqlite,
+}
+
+impl Dialect {
+    /// Quotes an identifier (column or field name) per dialect, escaping any
+    /// embedded quote characters so reserved words and dotted paths survive.
+    pub fn quote_ident(&self, ident: &str) -> String {
+        match self {
+            Dialect::Postgres | Dialect::Sqlite => format!("\"{}\"", ident.replace('"', "\"\"")),
+            Dialect::MySql => format!("`{}`", ident.replace('`', "``")),
+        }
+    }
+
+    /// Renders a JSON value as a SQL literal: single-quoted and escaped for
+    /// strings, bare for numbers/booleans, `NULL` for null.
+    pub fn quote_literal(&self, value: &Value) -> String {
+        match value {
+            Value::String(s) => format!("'{}'", s.replace('\'', "''")),
+            Value::Null => "NULL".to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    fn quote_literal_list(&self, value: &Value) -> Vec<String> {
+        match value {
+            Value::Array(a) => a.iter().map(|v| self.quote_literal(v)).collect(),
+            other => vec![self.quote_literal(other)],
+        }
+    }
+
+    /// Validates the `$options` flag string (`i` case-insensitive, `m`
+    /// multiline) and returns the dialect's comparison operator alongside the
+    /// pattern text to bind/quote, folding flags that have no dedicated
+    /// operator into an inline `(?im)` prefix. Unsupported flags are rejected
+    /// rather than silently dropped. Shared by [`Dialect::regex_clause`] (the
+    /// inline path) and `match_stage_params` (the parameterized path), so
+    /// both surfaces honor flags identically.
+    fn regex_operator_and_pattern(
+        &self,
+        pattern: &str,
+        options: Option<&str>,
+    ) -> Result<(&'static str, String), ToSqlError> {
+        let flags = options.unwrap_or("");
+        for flag in flags.chars() {
+            if flag != 'i' && flag != 'm' {
+                return Err(ToSqlError::UnsupportedRegexFlag(flag));
+            }
+        }
+        match self {
+            Dialect::Postgres => {
+                if flags.is_empty() {
+                    Ok(("~", pattern.to_string()))
+                } else if flags == "i" {
+                    Ok(("~*", pattern.to_string()))
+                } else {
+                    Ok(("~", format!("(?{}){}", flags, pattern)))
+                }
+            }
+            // MySQL's `REGEXP` has no case-insensitive variant of the operator
+            // itself, but its ICU regex engine accepts inline mode modifiers,
+            // so flags fold into the pattern rather than being dropped.
+            Dialect::MySql => {
+                if flags.is_empty() {
+                    Ok(("REGEXP", pattern.to_string()))
+                } else {
+                    Ok(("REGEXP", format!("(?{}){}", flags, pattern)))
+                }
+            }
+            Dialect::Sqlite => Err(ToSqlError::UnsupportedOperator(
+                "$regex (SQLite has no native regex operator; $regex is not translated for SQLite)"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Renders a `$regex` match against `field_sql` (already identifier-quoted),
+    /// honoring the sibling `$options` flag string.
+    fn regex_clause(
+        &self,
+        field_sql: &str,
+        pattern: &str,
+        options: Option<&str>,
+    ) -> Result<String, ToSqlError> {
+        let (operator, literal_pattern) = self.regex_operator_and_pattern(pattern, options)?;
+        Ok(format!(
+            "{} {} {}",
+            field_sql,
+            operator,
+            self.quote_str_literal(&literal_pattern)
+        ))
+    }
+
+    fn quote_str_literal(&self, s: &str) -> String {
+        self.quote_literal(&Value::String(s.to_string()))
+    }
+
+    /// The largest representable `LIMIT` value, used as a "no limit" sentinel
+    /// when a `$skip` is given without a `$limit`: MySQL and SQLite both
+    /// require a `LIMIT` clause before `OFFSET` is syntactically valid.
+    fn max_limit_sentinel(&self) -> &'static str {
+        match self {
+            Dialect::Postgres => "ALL",
+            Dialect::MySql => "18446744073709551615",
+            Dialect::Sqlite => "-1",
+        }
+    }
+
+    /// The positional bind-parameter placeholder for the `n`th (1-indexed)
+    /// parameter: `$n` for Postgres, `?` for the others.
+    fn placeholder(&self, n: usize) -> String {
+        match self {
+            Dialect::Postgres => format!("${}", n),
+            Dialect::MySql | Dialect::Sqlite => "?".to_string(),
+        }
+    }
+
+    /// Renders a field reference for use in a SQL expression. Plain field
+    /// names are just identifier-quoted; dotted Mongo paths (`address.city`)
+    /// are translated into the dialect's JSON path accessor against the
+    /// leading column, so documents stored in a JSON/JSONB column can be
+    /// queried as if the nested field were a normal column.
+    fn field_accessor(&self, field: &str) -> String {
+        if !field.contains('.') {
+            return self.quote_ident(field);
+        }
+        let mut segments = field.split('.');
+        let column = self.quote_ident(segments.next().unwrap());
+        // Path segments land inside a single-quoted literal below, so they
+        // need the same escaping as any other string literal.
+        let path: Vec<String> = segments.map(|s| s.replace('\'', "''")).collect();
+        match self {
+            Dialect::Postgres => {
+                if path.len() == 1 {
+                    format!("{}->>'{}'", column, path[0])
+                } else {
+                    format!("{}#>>'{{{}}}'", column, path.join(","))
+                }
+            }
+            Dialect::MySql | Dialect::Sqlite => {
+                format!("{}->>'$.{}'", column, path.join("."))
+            }
+        }
+    }
+}
+
+/// An operator handler renders a single `$operator: operand` pair for a given,
+/// already dialect-quoted field into a SQL condition, e.g.
+/// `("age", json!(21), Dialect::Postgres)` -> `"age = 21"`.
+pub type OperatorHandler =
+    fn(field: &str, operand: &Value, dialect: Dialect) -> Result<String, ToSqlError>;
+
+/// Translates `$match`-style stages into SQL using a registry of operator
+/// handlers, so new `$`-operators can be added without touching this crate.
+pub struct Translator {
+    operators: HashMap<&'static str, OperatorHandler>,
+}
+
+impl Translator {
+    pub fn new() -> Self {
+        let mut operators: HashMap<&'static str, OperatorHandler> = HashMap::new();
+        operators.insert("$gte", op_gte as OperatorHandler);
+        operators.insert("$gt", op_gt as OperatorHandler);
+        operators.insert("$lte", op_lte as OperatorHandler);
+        operators.insert("$lt", op_lt as OperatorHandler);
+        operators.insert("$eq", op_eq as OperatorHandler);
+        operators.insert("$ne", op_ne as OperatorHandler);
+        operators.insert("$in", op_in as OperatorHandler);
+        operators.insert("$nin", op_nin as OperatorHandler);
+        operators.insert("$regex", op_regex as OperatorHandler);
+        operators.insert("$exists", op_exists as OperatorHandler);
+        Translator { operators }
+    }
+
+    /// Registers a handler for a custom `$`-operator, overriding any built-in
+    /// handler already registered under that name.
+    pub fn register_operator(&mut self, name: &'static str, handler: OperatorHandler) {
+        self.operators.insert(name, handler);
+    }
+
+    pub fn match_stage(&self, stage: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+        self.match_stage_with_resolver(stage, dialect, &|_| None)
+    }
+
+    /// Like [`Translator::match_stage`], but `resolve_field` gets first crack
+    /// at rendering a field reference; returning `None` falls back to
+    /// `dialect.field_accessor`. This is how `pipeline_to_sql` points a
+    /// post-`$group` `$match` at the underlying aggregate expression (e.g.
+    /// `SUM("amount")`) instead of the SELECT-list alias SQL can't resolve in a
+    /// HAVING clause.
+    fn match_stage_with_resolver(
+        &self,
+        stage: &Value,
+        dialect: Dialect,
+        resolve_field: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<String, ToSqlError> {
+        let mut sql = String::new();
+        if let Some(stage_obj) = stage.as_object() {
+            let op_keys = ["$and", "$or", "$nor"];
+            let mut op_values: Vec<&Value> = Vec::new();
+            for (key, value) in stage_obj.iter() {
+                if op_keys.contains(&key.as_str()) {
+                    if let Value::Array(a) = value {
+                        op_values = a.iter().collect();
+                    } else {
+                        return Err(ToSqlError::InvalidOperandValue(key.to_string()));
+                    }
+                } else if let Value::Object(op) = value {
+                    // `$regex` and `$options` are two keys of the same operand
+                    // object, so they must be inspected together rather than
+                    // picking whichever key iteration happens to surface first.
+                    if op.contains_key("$regex") {
+                        let handler = self
+                            .operators
+                            .get("$regex")
+                            .ok_or_else(|| ToSqlError::UnsupportedOperator("$regex".to_string()))?;
+                        let field =
+                            resolve_field(key).unwrap_or_else(|| dialect.field_accessor(key));
+                        sql.push_str(&handler(&field, value, dialect)?);
+                        continue;
+                    }
+                    if let Some(op_key) = op.keys().next() {
+                        let op_value = op.get(op_key).unwrap();
+                        if op_key == "$options" {
+                            continue;
+                        }
+                        let handler = self
+                            .operators
+                            .get(op_key.as_str())
+                            .ok_or_else(|| ToSqlError::UnsupportedOperator(op_key.to_string()))?;
+                        let field =
+                            resolve_field(key).unwrap_or_else(|| dialect.field_accessor(key));
+                        sql.push_str(&handler(&field, op_value, dialect)?);
+                    } else {
+                        return Err(ToSqlError::MissingOperator(key.to_string()));
+                    }
+                } else {
+                    let field = resolve_field(key).unwrap_or_else(|| dialect.field_accessor(key));
+                    sql.push_str(&format!("{} = {}", field, dialect.quote_literal(value)));
+                }
+            }
+            if !op_values.is_empty() {
+                let sub_sql = op_values
+                    .iter()
+                    .map(|sub_stage| {
+                        self.match_stage_with_resolver(sub_stage, dialect, resolve_field)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+                    .iter()
+                    .map(|s| format!("({})", s))
+                    .collect::<Vec<_>>();
+                let joiner = if stage_obj.contains_key("$and") {
+                    " AND "
+                } else {
+                    " OR "
+                };
+                let sub_sql = sub_sql.join(joiner);
+                if stage_obj.contains_key("$nor") {
+                    sql.push_str(&format!("NOT ({})", sub_sql));
+                } else {
+                    sql.push_str(&format!("({})", sub_sql));
+                }
+            }
+        } else {
+            return Err(ToSqlError::InvalidStage(stage.to_owned()));
+        }
+        Ok(sql)
+    }
+}
+
+impl Default for Translator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn op_gte(field: &str, operand: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+    Ok(format!("{} >= {}", field, dialect.quote_literal(operand)))
+}
+
+fn op_gt(field: &str, operand: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+    Ok(format!("{} > {}", field, dialect.quote_literal(operand)))
+}
+
+fn op_lte(field: &str, operand: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+    Ok(format!("{} <= {}", field, dialect.quote_literal(operand)))
+}
+
+fn op_lt(field: &str, operand: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+    Ok(format!("{} < {}", field, dialect.quote_literal(operand)))
 }
 
-pub fn match_stage(stage: &serde_json::Value) -> Result<String, ToSqlError> {
+fn op_eq(field: &str, operand: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+    Ok(format!("{} = {}", field, dialect.quote_literal(operand)))
+}
+
+fn op_ne(field: &str, operand: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+    Ok(format!("{} != {}", field, dialect.quote_literal(operand)))
+}
+
+fn op_in(field: &str, operand: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+    let vals = dialect.quote_literal_list(operand);
+    Ok(format!("{} IN ({})", field, vals.join(", ")))
+}
+
+fn op_nin(field: &str, operand: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+    let vals = dialect.quote_literal_list(operand);
+    Ok(format!("{} NOT IN ({})", field, vals.join(", ")))
+}
+
+/// `operand` is the full `{ "$regex": ..., "$options": ... }` object so the
+/// handler can honor flags rather than just the pattern.
+fn op_regex(field: &str, operand: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+    let obj = operand
+        .as_object()
+        .ok_or_else(|| ToSqlError::InvalidRegexValue(operand.clone()))?;
+    let pattern = obj
+        .get("$regex")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| ToSqlError::InvalidRegexValue(operand.clone()))?;
+    let options = obj.get("$options").and_then(|v| v.as_str());
+    dialect.regex_clause(field, pattern, options)
+}
+
+/// `$exists: true` -> `field IS NOT NULL`, `$exists: false` -> `field IS NULL`.
+fn op_exists(field: &str, operand: &Value, _dialect: Dialect) -> Result<String, ToSqlError> {
+    match operand.as_bool() {
+        Some(true) => Ok(format!("{} IS NOT NULL", field)),
+        Some(false) => Ok(format!("{} IS NULL", field)),
+        None => Err(ToSqlError::InvalidOperandValue(field.to_string())),
+    }
+}
+
+pub fn match_stage(stage: &Value, dialect: Dialect) -> Result<String, ToSqlError> {
+    Translator::new().match_stage(stage, dialect)
+}
+
+/// A stage's position in the fixed clause order a SELECT statement is
+/// assembled in: stages must appear in non-decreasing order so e.g. a
+/// `$group` can't follow a `$sort`.
+#[derive(PartialEq, PartialOrd)]
+enum ClauseRank {
+    Where,
+    GroupBy,
+    Having,
+    OrderBy,
+    LimitOffset,
+}
+
+/// Translates an ordered aggregation pipeline into a single SELECT statement
+/// against `collection`, the way `arel` composes `select`/`where`/`having`
+/// clauses from independent pieces: `$match` becomes WHERE (or HAVING, if it
+/// follows a `$group`), `$project` becomes the select list, `$group` becomes
+/// GROUP BY plus aggregates, `$sort` becomes ORDER BY, and `$limit`/`$skip`
+/// become LIMIT/OFFSET.
+pub fn pipeline_to_sql(
+    collection: &str,
+    stages: &[Value],
+    dialect: Dialect,
+) -> Result<String, ToSqlError> {
+    let translator = Translator::new();
+    let mut select_cols: Vec<String> = Vec::new();
+    let mut group_by: Option<String> = None;
+    let mut where_clause: Option<String> = None;
+    let mut having_clause: Option<String> = None;
+    let mut order_by: Option<String> = None;
+    let mut limit: Option<i64> = None;
+    let mut offset: Option<i64> = None;
+    // Maps a `$group` output field to the aggregate expression it was built
+    // from, so a later `$match` (-> HAVING) can reference the aggregate
+    // itself instead of the SELECT-list alias, which HAVING can't resolve.
+    let mut group_aliases: HashMap<String, String> = HashMap::new();
+
+    let mut max_rank = ClauseRank::Where;
+    let mut seen_group = false;
+
+    for stage in stages {
+        let stage_obj = stage
+            .as_object()
+            .ok_or_else(|| ToSqlError::InvalidStage(stage.to_owned()))?;
+        let (stage_name, stage_value) = stage_obj
+            .iter()
+            .next()
+            .ok_or_else(|| ToSqlError::InvalidStage(stage.to_owned()))?;
+
+        let rank = match stage_name.as_str() {
+            "$match" if !seen_group => ClauseRank::Where,
+            "$match" => ClauseRank::Having,
+            "$project" => ClauseRank::Where,
+            "$group" => ClauseRank::GroupBy,
+            "$sort" => ClauseRank::OrderBy,
+            "$limit" | "$skip" => ClauseRank::LimitOffset,
+            other => return Err(ToSqlError::UnsupportedOperator(other.to_string())),
+        };
+        if rank < max_rank {
+            return Err(ToSqlError::UnplaceableStage(stage_name.to_string()));
+        }
+        max_rank = match rank {
+            ClauseRank::Where => max_rank,
+            ClauseRank::GroupBy => ClauseRank::GroupBy,
+            ClauseRank::Having => ClauseRank::Having,
+            ClauseRank::OrderBy => ClauseRank::OrderBy,
+            ClauseRank::LimitOffset => ClauseRank::LimitOffset,
+        };
+
+        match stage_name.as_str() {
+            "$match" if !seen_group => {
+                where_clause = Some(translator.match_stage(stage_value, dialect)?)
+            }
+            "$match" => {
+                let resolve_field =
+                    |field: &str| -> Option<String> { group_aliases.get(field).cloned() };
+                having_clause = Some(translator.match_stage_with_resolver(
+                    stage_value,
+                    dialect,
+                    &resolve_field,
+                )?)
+            }
+            "$project" => {
+                let fields = stage_value
+                    .as_object()
+                    .ok_or_else(|| ToSqlError::InvalidStage(stage_value.to_owned()))?;
+                select_cols = fields
+                    .iter()
+                    .filter(|(_, included)| {
+                        matches!(included, Value::Number(n) if n.as_i64() != Some(0))
+                            || matches!(included, Value::Bool(true))
+                    })
+                    .map(|(field, _)| dialect.field_accessor(field))
+                    .collect();
+                // An exclusion-only `$project` (e.g. `{"password": 0}`) has no
+                // inclusion keys, so `select_cols` would fall through to
+                // `SELECT *` below and leak the excluded column right back in.
+                // We don't implement real column exclusion, so reject it
+                // outright rather than silently ignoring the user's intent.
+                if select_cols.is_empty() && !fields.is_empty() {
+                    return Err(ToSqlError::UnsupportedExclusionProjection(
+                        stage_value.to_owned(),
+                    ));
+                }
+            }
+            "$group" => {
+                seen_group = true;
+                let fields = stage_value
+                    .as_object()
+                    .ok_or_else(|| ToSqlError::InvalidStage(stage_value.to_owned()))?;
+                let mut group_select = Vec::new();
+                for (key, value) in fields.iter() {
+                    if key == "_id" && value.is_null() {
+                        // `{"_id": null}` is Mongo's "group everything into
+                        // one bucket" idiom — there's no grouping column, so
+                        // just skip GROUP BY and leave the aggregates bare.
+                    } else if key == "_id" {
+                        let id_field = strip_field_ref(value)?;
+                        let id_accessor = dialect.field_accessor(&id_field);
+                        group_by = Some(id_accessor.clone());
+                        group_aliases.insert("_id".to_string(), id_accessor.clone());
+                        group_select.push(id_accessor);
+                    } else {
+                        let expr = group_accumulator_expr(key, value, dialect)?;
+                        group_aliases.insert(key.to_string(), expr.clone());
+                        group_select.push(format!("{} AS {}", expr, dialect.quote_ident(key)));
+                    }
+                }
+                select_cols = group_select;
+            }
+            "$sort" => {
+                let fields = stage_value
+                    .as_object()
+                    .ok_or_else(|| ToSqlError::InvalidStage(stage_value.to_owned()))?;
+                order_by = Some(
+                    fields
+                        .iter()
+                        .map(|(field, direction)| {
+                            let dir = if direction.as_i64() == Some(-1) {
+                                "DESC"
+                            } else {
+                                "ASC"
+                            };
+                            format!("{} {}", dialect.field_accessor(field), dir)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                );
+            }
+            "$limit" => {
+                limit = Some(
+                    stage_value
+                        .as_i64()
+                        .ok_or_else(|| ToSqlError::InvalidOperandValue(stage_name.to_string()))?,
+                );
+            }
+            "$skip" => {
+                offset = Some(
+                    stage_value
+                        .as_i64()
+                        .ok_or_else(|| ToSqlError::InvalidOperandValue(stage_name.to_string()))?,
+                );
+            }
+            _ => unreachable!("validated above"),
+        }
+    }
+
+    let columns = if select_cols.is_empty() {
+        "*".to_string()
+    } else {
+        select_cols.join(", ")
+    };
+    let mut sql = format!("SELECT {} FROM {}", columns, collection);
+    if let Some(where_clause) = where_clause {
+        sql.push_str(&format!(" WHERE {}", where_clause));
+    }
+    if let Some(group_by) = group_by {
+        sql.push_str(&format!(" GROUP BY {}", group_by));
+    }
+    if let Some(having_clause) = having_clause {
+        sql.push_str(&format!(" HAVING {}", having_clause));
+    }
+    if let Some(order_by) = order_by {
+        sql.push_str(&format!(" ORDER BY {}", order_by));
+    }
+    if let Some(limit) = limit {
+        sql.push_str(&format!(" LIMIT {}", limit));
+    } else if offset.is_some() {
+        // A bare OFFSET with no LIMIT is a syntax error on MySQL/SQLite, so
+        // a `$skip`-only pipeline needs an explicit "no limit" sentinel.
+        sql.push_str(&format!(" LIMIT {}", dialect.max_limit_sentinel()));
+    }
+    if let Some(offset) = offset {
+        sql.push_str(&format!(" OFFSET {}", offset));
+    }
+    Ok(sql)
+}
+
+/// Strips a leading `$` from a Mongo field reference (e.g. `$status` -> `status`).
+fn strip_field_ref(value: &Value) -> Result<String, ToSqlError> {
+    value
+        .as_str()
+        .map(|s| s.strip_prefix('$').unwrap_or(s).to_string())
+        .ok_or_else(|| ToSqlError::InvalidOperandValue(value.to_string()))
+}
+
+/// Renders a `$group` accumulator (`{"$sum": "$amount"}`) into an aggregate
+/// expression (`SUM("amount")` on Postgres), without the `AS alias` — callers attach that
+/// themselves, since the bare expression is also what a following `$match`
+/// (-> HAVING) needs to reference.
+fn group_accumulator_expr(
+    alias: &str,
+    accumulator: &Value,
+    dialect: Dialect,
+) -> Result<String, ToSqlError> {
+    let obj = accumulator
+        .as_object()
+        .ok_or_else(|| ToSqlError::InvalidOperandValue(alias.to_string()))?;
+    let (op, operand) = obj
+        .iter()
+        .next()
+        .ok_or_else(|| ToSqlError::MissingOperator(alias.to_string()))?;
+    // Mongo's `{"$count": {}}` has no field operand — it counts rows.
+    if op == "$count" && operand.as_object().is_some_and(|o| o.is_empty()) {
+        return Ok("COUNT(*)".to_string());
+    }
+    let func = match op.as_str() {
+        "$sum" => "SUM",
+        "$avg" => "AVG",
+        "$min" => "MIN",
+        "$max" => "MAX",
+        "$count" => "COUNT",
+        other => return Err(ToSqlError::UnsupportedOperator(other.to_string())),
+    };
+    let expr = if operand.is_number() {
+        operand.to_string()
+    } else {
+        dialect.field_accessor(&strip_field_ref(operand)?)
+    };
+    Ok(format!("{}({})", func, expr))
+}
+
+/// Like [`match_stage`], but renders operand values as positional placeholders
+/// (`$1`, `$2`, ... for Postgres; `?` for MySQL/SQLite) instead of inlining
+/// them, returning the WHERE fragment alongside the ordered parameter values
+/// it refers to.
+///
+/// This is meant to be handed straight to a prepared-statement API (e.g.
+/// sqlx's `.bind()` calls) rather than executed as a raw string, so it avoids
+/// the quoting and injection pitfalls of [`match_stage`]. Fields are rendered
+/// through [`Dialect::field_accessor`], so dotted Mongo paths work the same
+/// way they do for the inline path.
+pub fn match_stage_params(
+    stage: &Value,
+    dialect: Dialect,
+) -> Result<(String, Vec<Value>), ToSqlError> {
+    let mut params = Vec::new();
+    let mut counter = 1usize;
+    let sql = match_stage_params_inner(stage, dialect, &mut params, &mut counter)?;
+    Ok((sql, params))
+}
+
+/// Operator dispatch here is a hardcoded `match` rather than the
+/// [`Translator`] registry used by [`match_stage_with_resolver`], since
+/// placeholder-binding needs `params`/`counter` threaded through every
+/// handler and the registry's [`OperatorHandler`] signature has no room for
+/// that. This means custom operators registered via
+/// [`Translator::register_operator`] aren't available here; only the
+/// operators handled below are.
+fn match_stage_params_inner(
+    stage: &Value,
+    dialect: Dialect,
+    params: &mut Vec<Value>,
+    counter: &mut usize,
+) -> Result<String, ToSqlError> {
     let mut sql = String::new();
     if let Some(stage_obj) = stage.as_object() {
         let op_keys = ["$and", "$or", "$nor"];
-        let mut op_values: Vec<&serde_json::Value> = Vec::new();
+        let mut op_values: Vec<&Value> = Vec::new();
         for (key, value) in stage_obj.iter() {
+            let field = dialect.field_accessor(key);
             if op_keys.contains(&key.as_str()) {
-                if let serde_json::Value::Array(a) = value {
+                if let Value::Array(a) = value {
                     op_values = a.iter().collect();
                 } else {
                     return Err(ToSqlError::InvalidOperandValue(key.to_string()));
                 }
-            } else if let serde_json::Value::Object(op) = value {
-                if let Some(op_key) = op.keys().next() {
+            } else if let Value::Object(op) = value {
+                // `$regex` and `$options` are two keys of the same operand
+                // object, so they must be inspected together rather than
+                // picking whichever key iteration happens to surface first.
+                if let Some(pattern) = op.get("$regex") {
+                    let pattern = pattern
+                        .as_str()
+                        .ok_or_else(|| ToSqlError::InvalidRegexValue(pattern.clone()))?;
+                    let options = op.get("$options").and_then(|v| v.as_str());
+                    let (operator, literal_pattern) =
+                        dialect.regex_operator_and_pattern(pattern, options)?;
+                    params.push(Value::String(literal_pattern));
+                    sql.push_str(&format!(
+                        "{} {} {}",
+                        field,
+                        operator,
+                        dialect.placeholder(*counter)
+                    ));
+                    *counter += 1;
+                } else if let Some(op_key) = op.keys().next() {
                     let op_value = op.get(op_key).unwrap();
                     match op_key.as_str() {
-                        "$gte" => sql.push_str(&format!("{} >= {}", key, op_value)),
-                        "$gt" => sql.push_str(&format!("{} > {}", key, op_value)),
-                        "$lte" => sql.push_str(&format!("{} <= {}", key, op_value)),
-                        "$lt" => sql.push_str(&format!("{} < {}", key, op_value)),
-                        "$eq" => sql.push_str(&format!("{} = {}", key, op_value)),
-                        "$ne" => sql.push_str(&format!("{} != {}", key, op_value)),
+                        "$gte" => sql.push_str(&next_placeholder_clause(
+                            &field, ">=", op_value, dialect, params, counter,
+                        )),
+                        "$gt" => sql.push_str(&next_placeholder_clause(
+                            &field, ">", op_value, dialect, params, counter,
+                        )),
+                        "$lte" => sql.push_str(&next_placeholder_clause(
+                            &field, "<=", op_value, dialect, params, counter,
+                        )),
+                        "$lt" => sql.push_str(&next_placeholder_clause(
+                            &field, "<", op_value, dialect, params, counter,
+                        )),
+                        "$eq" => sql.push_str(&next_placeholder_clause(
+                            &field, "=", op_value, dialect, params, counter,
+                        )),
+                        "$ne" => sql.push_str(&next_placeholder_clause(
+                            &field, "!=", op_value, dialect, params, counter,
+                        )),
                         "$in" => {
-                            let vals = match op_value {
-                                serde_json::Value::Array(a) => {
-                                    a.iter().map(|v| format!("{}", v)).collect::<Vec<_>>()
-                                }
-                                _ => vec![format!("{}", op_value)],
-                            };
-                            sql.push_str(&format!("{} IN ({})", key, vals.join(", ")));
+                            let placeholders =
+                                push_list_placeholders(op_value, dialect, params, counter);
+                            sql.push_str(&format!("{} IN ({})", field, placeholders.join(", ")));
                         }
                         "$nin" => {
-                            let vals = match op_value {
-                                serde_json::Value::Array(a) => {
-                                    a.iter().map(|v| format!("{}", v)).collect::<Vec<_>>()
+                            let placeholders =
+                                push_list_placeholders(op_value, dialect, params, counter);
+                            sql.push_str(&format!(
+                                "{} NOT IN ({})",
+                                field,
+                                placeholders.join(", ")
+                            ));
+                        }
+                        "$exists" => {
+                            sql.push_str(&match op_value.as_bool() {
+                                Some(true) => format!("{} IS NOT NULL", field),
+                                Some(false) => format!("{} IS NULL", field),
+                                None => {
+                                    return Err(ToSqlError::InvalidOperandValue(field.to_string()))
                                 }
-                                _ => vec![format!("{}", op_value)],
-                            };
-                            sql.push_str(&format!("{} NOT IN ({})", key, vals.join(", ")));
+                            });
                         }
-                        "$regex" => sql.push_str(&format!(
-                            "{} ~ '{}'",
-                            key,
-                            op_value
-                                .as_str()
-                                .ok_or_else(|| ToSqlError::InvalidRegexValue(op_value.clone()))?
-                        )),
                         "$options" => {}
                         _ => return Err(ToSqlError::UnsupportedOperator(op_key.to_string())),
                     }
@@ -69,23 +721,30 @@ pub fn match_stage(stage: &serde_json::Value) -> Result<String, ToSqlError> {
                     return Err(ToSqlError::MissingOperator(key.to_string()));
                 }
             } else {
-                sql.push_str(&format!("{} = {}", key, value));
+                params.push(value.clone());
+                sql.push_str(&format!("{} = {}", field, dialect.placeholder(*counter)));
+                *counter += 1;
             }
         }
         if !op_values.is_empty() {
             let sub_sql = op_values
                 .iter()
-                .map(|sub_stage| match_stage(sub_stage))
+                .map(|sub_stage| match_stage_params_inner(sub_stage, dialect, params, counter))
                 .collect::<Result<Vec<_>, _>>()?
                 .iter()
                 .map(|s| format!("({})", s))
                 .collect::<Vec<_>>();
-            let sub_sql = sub_sql.join(if stage_obj.contains_key("$and") {
+            let joiner = if stage_obj.contains_key("$and") {
                 " AND "
             } else {
                 " OR "
-            });
-            sql.push_str(&format!("({})", sub_sql));
+            };
+            let sub_sql = sub_sql.join(joiner);
+            if stage_obj.contains_key("$nor") {
+                sql.push_str(&format!("NOT ({})", sub_sql));
+            } else {
+                sql.push_str(&format!("({})", sub_sql));
+            }
         }
     } else {
         return Err(ToSqlError::InvalidStage(stage.to_owned()));
@@ -93,6 +752,41 @@ pub fn match_stage(stage: &serde_json::Value) -> Result<String, ToSqlError> {
     Ok(sql)
 }
 
+fn next_placeholder_clause(
+    field: &str,
+    op: &str,
+    value: &Value,
+    dialect: Dialect,
+    params: &mut Vec<Value>,
+    counter: &mut usize,
+) -> String {
+    params.push(value.clone());
+    let clause = format!("{} {} {}", field, op, dialect.placeholder(*counter));
+    *counter += 1;
+    clause
+}
+
+fn push_list_placeholders(
+    value: &Value,
+    dialect: Dialect,
+    params: &mut Vec<Value>,
+    counter: &mut usize,
+) -> Vec<String> {
+    let items: Vec<&Value> = match value {
+        Value::Array(a) => a.iter().collect(),
+        other => vec![other],
+    };
+    items
+        .into_iter()
+        .map(|item| {
+            params.push(item.clone());
+            let placeholder = dialect.placeholder(*counter);
+            *counter += 1;
+            placeholder
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
@@ -102,57 +796,57 @@ mod tests {
     #[test]
     fn test_match_stage_with_gte() {
         let stage = json!({ "age": { "$gte": 21 } });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "age >= 21");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"age\" >= 21");
     }
 
     #[test]
     fn test_match_stage_with_gt() {
         let stage = json!({ "age": { "$gt": 21 } });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "age > 21");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"age\" > 21");
     }
 
     #[test]
     fn test_match_stage_with_lte() {
         let stage = json!({ "age": { "$lte": 21 } });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "age <= 21");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"age\" <= 21");
     }
 
     #[test]
     fn test_match_stage_with_lt() {
         let stage = json!({ "age": { "$lt": 21 } });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "age < 21");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"age\" < 21");
     }
 
     #[test]
     fn test_match_stage_with_eq() {
         let stage = json!({ "name": { "$eq": "John" } });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "name = \"John\"");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"name\" = 'John'");
     }
 
     #[test]
     fn test_match_stage_with_ne() {
         let stage = json!({ "name": { "$ne": "John" } });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "name != \"John\"");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"name\" != 'John'");
     }
 
     #[test]
     fn test_match_stage_with_in() {
         let stage = json!({ "status": { "$in": ["active", "pending"] } });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "status IN (\"active\", \"pending\")");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"status\" IN ('active', 'pending')");
     }
 
     #[test]
     fn test_match_stage_with_nin() {
         let stage = json!({ "status": { "$nin": ["active", "pending"] } });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "status NOT IN (\"active\", \"pending\")");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"status\" NOT IN ('active', 'pending')");
     }
 
     #[test]
@@ -163,8 +857,8 @@ mod tests {
                 { "age": { "$gte": 21 } }
             ]
         });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "((status = \"active\") AND (age >= 21))");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "((\"status\" = 'active') AND (\"age\" >= 21))");
     }
 
     #[test]
@@ -175,26 +869,400 @@ mod tests {
                 { "age": { "$gte": 21 } }
             ]
         });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "((status = \"active\") OR (age >= 21))");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "((\"status\" = 'active') OR (\"age\" >= 21))");
+    }
+
+    #[test]
+    fn test_match_stage_with_nor() {
+        let stage = json!({
+            "$nor": [
+                { "status": "active" },
+                { "age": { "$gte": 21 } }
+            ]
+        });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "NOT ((\"status\" = 'active') OR (\"age\" >= 21))");
     }
 
     #[test]
     fn test_match_stage_with_regex() {
+        let stage = json!({ "name": { "$regex": "^joh?n$" } });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"name\" ~ '^joh?n$'");
+    }
+
+    #[test]
+    fn test_match_stage_with_regex_case_insensitive_option() {
         let stage = json!({
             "name": {
                 "$regex": "^joh?n$",
                 "$options": "i"
             }
         });
-        let sql = match_stage(&stage).unwrap();
-        assert_eq!(sql, "name ~ '^joh?n$'");
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"name\" ~* '^joh?n$'");
+    }
+
+    #[test]
+    fn test_match_stage_with_regex_combined_options() {
+        let stage = json!({
+            "name": {
+                "$regex": "^joh?n$",
+                "$options": "im"
+            }
+        });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"name\" ~ '(?im)^joh?n$'");
+    }
+
+    #[test]
+    fn test_match_stage_with_regex_unsupported_option_errors() {
+        let stage = json!({
+            "name": {
+                "$regex": "^joh?n$",
+                "$options": "x"
+            }
+        });
+        let res = match_stage(&stage, Dialect::Postgres);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_match_stage_with_regex_on_mysql() {
+        let stage = json!({ "name": { "$regex": "^joh?n$" } });
+        let sql = match_stage(&stage, Dialect::MySql).unwrap();
+        assert_eq!(sql, "`name` REGEXP '^joh?n$'");
+    }
+
+    #[test]
+    fn test_match_stage_with_regex_on_mysql_honors_options() {
+        let stage = json!({ "name": { "$regex": "^joh?n$", "$options": "i" } });
+        let sql = match_stage(&stage, Dialect::MySql).unwrap();
+        assert_eq!(sql, "`name` REGEXP '(?i)^joh?n$'");
+    }
+
+    #[test]
+    fn test_match_stage_with_regex_on_sqlite_errors() {
+        let stage = json!({ "name": { "$regex": "^joh?n$" } });
+        let res = match_stage(&stage, Dialect::Sqlite);
+        assert!(res.is_err());
     }
 
     #[test]
     fn test_match_stage_with_unsupported_operator() {
         let stage = json!({ "name": { "$foo": "bar" } });
-        let res = match_stage(&stage);
+        let res = match_stage(&stage, Dialect::Postgres);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_match_stage_with_exists_true() {
+        let stage = json!({ "email": { "$exists": true } });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"email\" IS NOT NULL");
+    }
+
+    #[test]
+    fn test_match_stage_with_exists_false() {
+        let stage = json!({ "email": { "$exists": false } });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"email\" IS NULL");
+    }
+
+    #[test]
+    fn test_match_stage_with_mysql_identifiers() {
+        let stage = json!({ "name": "John" });
+        let sql = match_stage(&stage, Dialect::MySql).unwrap();
+        assert_eq!(sql, "`name` = 'John'");
+    }
+
+    #[test]
+    fn test_match_stage_with_dotted_field_on_postgres() {
+        let stage = json!({ "address.city": "NYC" });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"address\"->>'city' = 'NYC'");
+    }
+
+    #[test]
+    fn test_match_stage_with_deeply_dotted_field_on_postgres() {
+        let stage = json!({ "address.geo.city": "NYC" });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"address\"#>>'{geo,city}' = 'NYC'");
+    }
+
+    #[test]
+    fn test_match_stage_with_dotted_field_in_comparison() {
+        let stage = json!({ "address.zip": { "$gte": 10001 } });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"address\"->>'zip' >= 10001");
+    }
+
+    #[test]
+    fn test_match_stage_with_dotted_field_in_in() {
+        let stage = json!({ "address.city": { "$in": ["NYC", "LA"] } });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"address\"->>'city' IN ('NYC', 'LA')");
+    }
+
+    #[test]
+    fn test_match_stage_with_dotted_field_in_regex() {
+        let stage = json!({ "address.city": { "$regex": "^N" } });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"address\"->>'city' ~ '^N'");
+    }
+
+    #[test]
+    fn test_match_stage_with_dotted_field_on_mysql() {
+        let stage = json!({ "address.city": "NYC" });
+        let sql = match_stage(&stage, Dialect::MySql).unwrap();
+        assert_eq!(sql, "`address`->>'$.city' = 'NYC'");
+    }
+
+    #[test]
+    fn test_match_stage_with_quote_in_dotted_path_segment_on_postgres() {
+        let stage = json!({ "o'brien.city": "NYC" });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"o'brien\"->>'city' = 'NYC'");
+    }
+
+    #[test]
+    fn test_match_stage_escapes_quote_in_nested_path_segment_on_postgres() {
+        let stage = json!({ "address.o'brien": "NYC" });
+        let sql = match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"address\"->>'o''brien' = 'NYC'");
+    }
+
+    #[test]
+    fn test_translator_register_operator() {
+        let mut translator = Translator::new();
+        translator.register_operator("$mod", |field, operand, dialect| {
+            let divisor = operand
+                .get(0)
+                .and_then(|v| v.as_i64())
+                .ok_or_else(|| ToSqlError::InvalidOperandValue(field.to_string()))?;
+            let remainder = operand
+                .get(1)
+                .and_then(|v| v.as_i64())
+                .ok_or_else(|| ToSqlError::InvalidOperandValue(field.to_string()))?;
+            let _ = dialect;
+            Ok(format!("{} % {} = {}", field, divisor, remainder))
+        });
+        let stage = json!({ "age": { "$mod": [2, 0] } });
+        let sql = translator.match_stage(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"age\" % 2 = 0");
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_with_match_only() {
+        let stages = vec![json!({ "$match": { "age": { "$gte": 21 } } })];
+        let sql = pipeline_to_sql("users", &stages, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "SELECT * FROM users WHERE \"age\" >= 21");
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_with_project_sort_limit_skip() {
+        let stages = vec![
+            json!({ "$match": { "status": "active" } }),
+            json!({ "$project": { "name": 1, "age": 1 } }),
+            json!({ "$sort": { "age": -1 } }),
+            json!({ "$skip": 10 }),
+            json!({ "$limit": 5 }),
+        ];
+        let sql = pipeline_to_sql("users", &stages, Dialect::Postgres).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT \"name\", \"age\" FROM users WHERE \"status\" = 'active' ORDER BY \"age\" DESC LIMIT 5 OFFSET 10"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_with_dotted_sort_field() {
+        let stages = vec![json!({ "$sort": { "address.city": 1 } })];
+        let sql = pipeline_to_sql("users", &stages, Dialect::Postgres).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users ORDER BY \"address\"->>'city' ASC"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_with_group_and_having() {
+        let stages = vec![
+            json!({ "$group": { "_id": "$status", "total": { "$sum": 1 } } }),
+            json!({ "$match": { "total": { "$gt": 1 } } }),
+        ];
+        let sql = pipeline_to_sql("orders", &stages, Dialect::Postgres).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT \"status\", SUM(1) AS \"total\" FROM orders GROUP BY \"status\" HAVING SUM(1) > 1"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_with_null_id_groups_everything() {
+        let stages = vec![json!({ "$group": { "_id": null, "total": { "$sum": 1 } } })];
+        let sql = pipeline_to_sql("orders", &stages, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "SELECT SUM(1) AS \"total\" FROM orders");
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_with_count_accumulator() {
+        let stages = vec![json!({ "$group": { "_id": "$status", "n": { "$count": {} } } })];
+        let sql = pipeline_to_sql("orders", &stages, Dialect::Postgres).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT \"status\", COUNT(*) AS \"n\" FROM orders GROUP BY \"status\""
+        );
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_with_skip_only_on_postgres() {
+        let stages = vec![json!({ "$skip": 10 })];
+        let sql = pipeline_to_sql("users", &stages, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "SELECT * FROM users LIMIT ALL OFFSET 10");
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_with_skip_only_on_mysql() {
+        let stages = vec![json!({ "$skip": 10 })];
+        let sql = pipeline_to_sql("users", &stages, Dialect::MySql).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users LIMIT 18446744073709551615 OFFSET 10"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_with_skip_only_on_sqlite() {
+        let stages = vec![json!({ "$skip": 10 })];
+        let sql = pipeline_to_sql("users", &stages, Dialect::Sqlite).unwrap();
+        assert_eq!(sql, "SELECT * FROM users LIMIT -1 OFFSET 10");
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_rejects_exclusion_only_project() {
+        let stages = vec![json!({ "$project": { "password": 0 } })];
+        let res = pipeline_to_sql("users", &stages, Dialect::Postgres);
+        assert!(matches!(
+            res,
+            Err(ToSqlError::UnsupportedExclusionProjection(_))
+        ));
+    }
+
+    #[test]
+    fn test_pipeline_to_sql_rejects_group_after_sort() {
+        let stages = vec![
+            json!({ "$sort": { "age": 1 } }),
+            json!({ "$group": { "_id": "$status" } }),
+        ];
+        let res = pipeline_to_sql("users", &stages, Dialect::Postgres);
         assert!(res.is_err());
     }
+
+    #[test]
+    fn test_match_stage_params_with_simple_eq() {
+        let stage = json!({ "name": "John" });
+        let (sql, params) = match_stage_params(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"name\" = $1");
+        assert_eq!(params, vec![json!("John")]);
+    }
+
+    #[test]
+    fn test_match_stage_params_with_gte() {
+        let stage = json!({ "age": { "$gte": 21 } });
+        let (sql, params) = match_stage_params(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"age\" >= $1");
+        assert_eq!(params, vec![json!(21)]);
+    }
+
+    #[test]
+    fn test_match_stage_params_with_in() {
+        let stage = json!({ "status": { "$in": ["active", "pending"] } });
+        let (sql, params) = match_stage_params(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"status\" IN ($1, $2)");
+        assert_eq!(params, vec![json!("active"), json!("pending")]);
+    }
+
+    #[test]
+    fn test_match_stage_params_with_and_numbers_continuously() {
+        let stage = json!({
+            "$and": [
+                { "status": "active" },
+                { "age": { "$gte": 21 } }
+            ]
+        });
+        let (sql, params) = match_stage_params(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "((\"status\" = $1) AND (\"age\" >= $2))");
+        assert_eq!(params, vec![json!("active"), json!(21)]);
+    }
+
+    #[test]
+    fn test_match_stage_params_with_nested_or_and_in() {
+        let stage = json!({
+            "$or": [
+                { "status": { "$in": ["active", "pending"] } },
+                { "age": { "$lt": 18 } }
+            ]
+        });
+        let (sql, params) = match_stage_params(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "((\"status\" IN ($1, $2)) OR (\"age\" < $3))");
+        assert_eq!(
+            params,
+            vec![json!("active"), json!("pending"), json!(18)]
+        );
+    }
+
+    #[test]
+    fn test_match_stage_params_with_nor() {
+        let stage = json!({
+            "$nor": [
+                { "status": "active" },
+                { "age": { "$gte": 21 } }
+            ]
+        });
+        let (sql, params) = match_stage_params(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "NOT ((\"status\" = $1) OR (\"age\" >= $2))");
+        assert_eq!(params, vec![json!("active"), json!(21)]);
+    }
+
+    #[test]
+    fn test_match_stage_params_with_dotted_field() {
+        let stage = json!({ "address.city": "NYC" });
+        let (sql, params) = match_stage_params(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"address\"->>'city' = $1");
+        assert_eq!(params, vec![json!("NYC")]);
+    }
+
+    #[test]
+    fn test_match_stage_params_with_regex_options() {
+        let stage = json!({ "name": { "$regex": "^joh?n$", "$options": "i" } });
+        let (sql, params) = match_stage_params(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"name\" ~* $1");
+        assert_eq!(params, vec![json!("^joh?n$")]);
+    }
+
+    #[test]
+    fn test_match_stage_params_with_mysql_placeholder() {
+        let stage = json!({ "status": { "$in": ["active", "pending"] } });
+        let (sql, params) = match_stage_params(&stage, Dialect::MySql).unwrap();
+        assert_eq!(sql, "`status` IN (?, ?)");
+        assert_eq!(params, vec![json!("active"), json!("pending")]);
+    }
+
+    #[test]
+    fn test_match_stage_params_with_exists_true() {
+        let stage = json!({ "email": { "$exists": true } });
+        let (sql, params) = match_stage_params(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"email\" IS NOT NULL");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_match_stage_params_with_exists_false() {
+        let stage = json!({ "email": { "$exists": false } });
+        let (sql, params) = match_stage_params(&stage, Dialect::Postgres).unwrap();
+        assert_eq!(sql, "\"email\" IS NULL");
+        assert!(params.is_empty());
+    }
 }